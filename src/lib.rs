@@ -1,9 +1,38 @@
 use humantime::{self, Rfc3339Timestamp};
+use log::kv;
 use log::*;
 use serde_json::json;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
 use std::thread;
 use std::time::SystemTime;
 
+/// A user-supplied replacement for [`format_log_local`]/[`format_log_fiaas`],
+/// writing the full line (including the trailing newline) for `record` to
+/// `sink`. Installed via [`try_init_with_formatter`].
+type Formatter = dyn Fn(&mut dyn Write, &Record, &Rfc3339Timestamp) -> io::Result<()> + Send + Sync;
+
+/// Handle to the installed logger's configuration, set once by the first
+/// successful `try_init*` call. Lets [`reconfigure`] and [`set_filter`] swap
+/// the env/filter of an already-installed logger without a restart.
+static LOGGER: OnceLock<Arc<RwLock<FiaasLogger>>> = OnceLock::new();
+
+/// Fields already written by [`format_log_fiaas`]; a key-value pair using one
+/// of these names is namespaced as `field_<name>` instead of overwriting it.
+const RESERVED_FIELDS: &[&str] = &[
+    "@version",
+    "@timestamp",
+    "logger",
+    "thread",
+    "level",
+    "message",
+    "finn_app",
+];
+
 pub use log::Level;
 
 pub enum FiaasEnv {
@@ -12,10 +41,200 @@ pub enum FiaasEnv {
     Prod,
 }
 
+/// Where formatted log lines are written.
+enum Output {
+    /// stdout, or stderr for [`Level::Error`] records (the original behavior).
+    Stdio,
+    File {
+        writer: Mutex<BufWriter<File>>,
+    },
+    /// Hands lines to a dedicated writer thread instead of blocking the
+    /// calling thread on stdio. See [`try_init_nonblocking`].
+    NonBlocking(NonBlockingWriter),
+}
+
+/// What happens to a log line when the non-blocking writer's queue is full.
+pub enum OverflowPolicy {
+    /// Block the calling thread until the writer thread drains the queue.
+    Block,
+    /// Drop the line and count it; see [`dropped_log_count`].
+    Drop,
+}
+
+/// A line, or a flush request, sent to the background writer thread spawned
+/// by [`try_init_nonblocking`].
+enum WriterMsg {
+    Line { line: String, is_error: bool },
+    Flush(mpsc::Sender<()>),
+}
+
+/// The [`Output::NonBlocking`] side of the channel to the writer thread.
+struct NonBlockingWriter {
+    sender: SyncSender<WriterMsg>,
+    overflow: OverflowPolicy,
+    dropped: Arc<AtomicU64>,
+}
+
+impl NonBlockingWriter {
+    fn enqueue(&self, line: String, is_error: bool) -> io::Result<()> {
+        let disconnected =
+            || io::Error::new(io::ErrorKind::BrokenPipe, "log writer thread is gone");
+        let msg = WriterMsg::Line { line, is_error };
+        match self.overflow {
+            OverflowPolicy::Block => self.sender.send(msg).map_err(|_| disconnected()),
+            OverflowPolicy::Drop => match self.sender.try_send(msg) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Full(_)) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    Ok(())
+                }
+                Err(TrySendError::Disconnected(_)) => Err(disconnected()),
+            },
+        }
+    }
+
+    fn flush(&self) {
+        let (tx, rx) = mpsc::channel();
+        if self.sender.send(WriterMsg::Flush(tx)).is_ok() {
+            let _ = rx.recv();
+        }
+    }
+}
+
+fn spawn_writer_thread(receiver: mpsc::Receiver<WriterMsg>) {
+    thread::spawn(move || {
+        let stdout = io::stdout();
+        let stderr = io::stderr();
+        for msg in receiver {
+            match msg {
+                WriterMsg::Line { line, is_error } => {
+                    let result = if is_error {
+                        writeln!(stderr.lock(), "{}", line)
+                    } else {
+                        writeln!(stdout.lock(), "{}", line)
+                    };
+                    if let Err(e) = result {
+                        eprintln!("fiaas-logger: failed to write log line: {}", e);
+                    }
+                }
+                WriterMsg::Flush(ack) => {
+                    let _ = stdout.lock().flush();
+                    let _ = stderr.lock().flush();
+                    let _ = ack.send(());
+                }
+            }
+        }
+    });
+}
+
+/// How [`try_init_to_file`] should treat a file that already exists at `path`.
+pub enum IfExists {
+    Append,
+    Truncate,
+    Fail,
+}
+
+impl IfExists {
+    fn open(&self, path: &Path) -> io::Result<File> {
+        match self {
+            IfExists::Append => OpenOptions::new().create(true).append(true).open(path),
+            IfExists::Truncate => OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(path),
+            IfExists::Fail => OpenOptions::new().create_new(true).write(true).open(path),
+        }
+    }
+}
+
+/// A single `target=level` directive parsed from a filter spec, e.g. `hyper=warn`.
+struct Directive {
+    module_prefix: String,
+    level: LevelFilter,
+}
+
+/// A parsed `RUST_LOG`-style filter spec: a default level plus per-target overrides.
+///
+/// Overrides are matched by the longest `module_prefix` that is a prefix of the
+/// record's target, mirroring `env_logger`'s directive matching.
+struct FilterSpec {
+    default: LevelFilter,
+    directives: Vec<Directive>,
+}
+
+impl FilterSpec {
+    /// Parses a spec like `info,hyper=warn,my_app::db=trace`.
+    ///
+    /// A bare level (no `=`) sets the default; the last one wins. Panics if a
+    /// level name isn't one of `off`, `error`, `warn`, `info`, `debug`, `trace`.
+    fn parse(spec: &str) -> FilterSpec {
+        let mut default = LevelFilter::Off;
+        let mut directives = Vec::new();
+
+        for part in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match part.split_once('=') {
+                Some((module_prefix, level)) => directives.push(Directive {
+                    module_prefix: module_prefix.to_string(),
+                    level: parse_level_filter(level),
+                }),
+                None => default = parse_level_filter(part),
+            }
+        }
+
+        // Longest prefix first, so the first match in `level_for` is the most specific.
+        directives.sort_by_key(|d| std::cmp::Reverse(d.module_prefix.len()));
+
+        FilterSpec {
+            default,
+            directives,
+        }
+    }
+
+    fn from_level(level: Level) -> FilterSpec {
+        FilterSpec {
+            default: level.to_level_filter(),
+            directives: Vec::new(),
+        }
+    }
+
+    /// The level filter that applies to a record emitted from `target`.
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .find(|d| target.starts_with(d.module_prefix.as_str()))
+            .map(|d| d.level)
+            .unwrap_or(self.default)
+    }
+
+    /// The loosest level filter across the default and all directives, suitable
+    /// for `log::set_max_level`.
+    fn max_level(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .map(|d| d.level)
+            .fold(self.default, std::cmp::max)
+    }
+}
+
+fn parse_level_filter(s: &str) -> LevelFilter {
+    match s.to_lowercase().as_str() {
+        "off" => LevelFilter::Off,
+        "error" => LevelFilter::Error,
+        "warn" => LevelFilter::Warn,
+        "info" => LevelFilter::Info,
+        "debug" => LevelFilter::Debug,
+        "trace" => LevelFilter::Trace,
+        _ => panic!("log level filter must be one of off, error, warn, info, debug and trace"),
+    }
+}
+
 struct FiaasLogger {
     finn_app: &'static str,
     env: FiaasEnv,
-    level: log::Level,
+    filter: FilterSpec,
+    formatter: Option<Box<Formatter>>,
+    output: Output,
 }
 
 fn format_log_local(timestamp: &Rfc3339Timestamp, record: &Record) -> String {
@@ -28,9 +247,47 @@ fn format_log_local(timestamp: &Rfc3339Timestamp, record: &Record) -> String {
     )
 }
 
+/// Collects the key-value pairs attached to a record (via `log::kv`) into a
+/// JSON map, namespacing any key that collides with a reserved top-level field.
+struct JsonKvVisitor<'a> {
+    fields: &'a mut serde_json::Map<String, serde_json::Value>,
+}
+
+impl<'kvs, 'a> kv::Visitor<'kvs> for JsonKvVisitor<'a> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let key = key.to_string();
+        let key = if RESERVED_FIELDS.contains(&key.as_str()) {
+            format!("field_{}", key)
+        } else {
+            key
+        };
+        self.fields.insert(key, kv_value_to_json(&value));
+        Ok(())
+    }
+}
+
+fn kv_value_to_json(value: &kv::Value) -> serde_json::Value {
+    if let Some(v) = value.to_bool() {
+        json!(v)
+    } else if let Some(v) = value.to_u64() {
+        json!(v)
+    } else if let Some(v) = value.to_i64() {
+        json!(v)
+    } else if let Some(v) = value.to_f64() {
+        json!(v)
+    } else {
+        json!(value.to_string())
+    }
+}
+
 fn format_log_fiaas(timestamp: &Rfc3339Timestamp, record: &Record, finn_app: &str) -> String {
     let t = thread::current();
-    serde_json::to_string(&json!({
+    let mut fields = serde_json::Map::new();
+    let _ = record.key_values().visit(&mut JsonKvVisitor {
+        fields: &mut fields,
+    });
+
+    let mut object = json!({
       "@version":1,
       "@timestamp": &timestamp.to_string(),
       "logger": record.target(),
@@ -38,38 +295,107 @@ fn format_log_fiaas(timestamp: &Rfc3339Timestamp, record: &Record, finn_app: &st
       "level": record.level().to_string(),
       "message": record.args(),
       "finn_app": finn_app,
-    }))
-    .unwrap()
+    });
+
+    if let serde_json::Value::Object(object) = &mut object {
+        object.extend(fields);
+    }
+
+    serde_json::to_string(&object).unwrap()
+}
+
+impl FiaasLogger {
+    /// Writes the formatted line for `record` to `sink`, via `self.formatter`
+    /// when set, falling back to [`format_log_local`]/[`format_log_fiaas`].
+    fn write_line(
+        &self,
+        sink: &mut dyn Write,
+        record: &Record,
+        timestamp: &Rfc3339Timestamp,
+    ) -> io::Result<()> {
+        if let Some(formatter) = &self.formatter {
+            return formatter(sink, record, timestamp);
+        }
+
+        let message = match self.env {
+            FiaasEnv::Local => format_log_local(timestamp, record),
+            FiaasEnv::Dev | FiaasEnv::Prod => format_log_fiaas(timestamp, record, self.finn_app),
+        };
+        writeln!(sink, "{}", message)
+    }
 }
 
 impl Log for FiaasLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= self.level
+        metadata.level() <= self.filter.level_for(metadata.target())
     }
 
     fn log(&self, record: &Record) {
-        if self.enabled(record.metadata()) {
-            let timestamp = humantime::format_rfc3339_millis(SystemTime::now());
-            match self.env {
-                FiaasEnv::Local => {
-                    let message = format_log_local(&timestamp, record);
-                    match record.level() {
-                        Level::Error => eprintln!("{}", &message),
-                        _ => println!("{}", &message),
-                    }
-                }
-                FiaasEnv::Dev | FiaasEnv::Prod => {
-                    let message = format_log_fiaas(&timestamp, record, &self.finn_app);
-                    match record.level() {
-                        Level::Error => eprintln!("{}", &message),
-                        _ => println!("{}", &message),
-                    }
-                }
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = humantime::format_rfc3339_millis(SystemTime::now());
+        let result = match &self.output {
+            Output::Stdio if record.level() == Level::Error => {
+                self.write_line(&mut io::stderr().lock(), record, &timestamp)
             }
+            Output::Stdio => self.write_line(&mut io::stdout().lock(), record, &timestamp),
+            Output::File { writer } => {
+                self.write_line(&mut *writer.lock().unwrap(), record, &timestamp)
+            }
+            Output::NonBlocking(writer) => {
+                let mut buf = Vec::new();
+                self.write_line(&mut buf, record, &timestamp)
+                    .and_then(|()| {
+                        let line = String::from_utf8(buf)
+                            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                        // `write_line` already appended a trailing newline; the
+                        // writer thread adds its own when it writes the line out.
+                        let line = line.strip_suffix('\n').unwrap_or(&line).to_string();
+                        writer.enqueue(line, record.level() == Level::Error)
+                    })
+            }
+        };
+
+        if let Err(e) = result {
+            eprintln!("fiaas-logger: failed to write log line: {}", e);
         }
     }
 
-    fn flush(&self) {}
+    fn flush(&self) {
+        match &self.output {
+            Output::File { writer } => {
+                let _ = writer.lock().unwrap().flush();
+            }
+            Output::NonBlocking(writer) => writer.flush(),
+            Output::Stdio => {}
+        }
+    }
+}
+
+/// The `log::Log` actually installed via `log::set_boxed_logger`.
+///
+/// Delegates to the shared, lock-protected [`FiaasLogger`] so that
+/// [`reconfigure`] and [`set_filter`] can swap its configuration in place,
+/// following the shim pattern solana-logger uses to allow reconfiguring a
+/// logger that `log` otherwise only lets you install once.
+struct FiaasLoggerShim {
+    inner: Arc<RwLock<FiaasLogger>>,
+}
+
+impl Log for FiaasLoggerShim {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.read().unwrap().enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.inner.read().unwrap().log(record)
+    }
+
+    fn flush(&self) {
+        self.inner.read().unwrap().flush()
+    }
 }
 
 pub fn try_init(
@@ -77,33 +403,167 @@ pub fn try_init(
     env: FiaasEnv,
     level: Level,
 ) -> Result<(), log::SetLoggerError> {
-    let r = log::set_boxed_logger(Box::new(FiaasLogger {
+    try_init_with_filter(finn_app, env, FilterSpec::from_level(level))
+}
+
+pub fn init(finn_app: &'static str, env: FiaasEnv, level: Level) {
+    try_init(finn_app, env, level).unwrap();
+}
+
+/// Like [`try_init`], but `spec` is an `env_logger`-style filter directive
+/// string, e.g. `info,hyper=warn,my_app::db=trace`: comma-separated
+/// `target=level` pairs, where a record is logged if its level is at least
+/// as severe as the filter for the longest prefix of its target that
+/// matches, falling back to the bare default level when nothing matches.
+pub fn init_with_filter(
+    finn_app: &'static str,
+    env: FiaasEnv,
+    spec: &str,
+) -> Result<(), log::SetLoggerError> {
+    try_init_with_filter(finn_app, env, FilterSpec::parse(spec))
+}
+
+/// Like [`try_init`], but every record is written by calling `formatter`
+/// instead of [`format_log_local`]/[`format_log_fiaas`]. `formatter` must
+/// write the complete line, including the trailing newline, to the given
+/// sink (stdout, or stderr for [`Level::Error`] records).
+pub fn try_init_with_formatter<F>(
+    finn_app: &'static str,
+    env: FiaasEnv,
+    level: Level,
+    formatter: F,
+) -> Result<(), log::SetLoggerError>
+where
+    F: Fn(&mut dyn Write, &Record, &Rfc3339Timestamp) -> io::Result<()> + Send + Sync + 'static,
+{
+    try_init_inner(
+        finn_app,
+        env,
+        FilterSpec::from_level(level),
+        Some(Box::new(formatter)),
+        Output::Stdio,
+    )
+}
+
+/// Like [`try_init`], but every record is written to the file at `path`
+/// instead of stdout/stderr, through a buffered writer that [`Log::flush`]
+/// flushes. `if_exists` controls whether an existing file is appended to,
+/// truncated, or treated as an error.
+pub fn try_init_to_file(
+    finn_app: &'static str,
+    env: FiaasEnv,
+    level: Level,
+    path: impl AsRef<Path>,
+    if_exists: IfExists,
+) -> io::Result<()> {
+    let file = if_exists.open(path.as_ref())?;
+    let output = Output::File {
+        writer: Mutex::new(BufWriter::new(file)),
+    };
+    try_init_inner(finn_app, env, FilterSpec::from_level(level), None, output)
+        .map_err(io::Error::other)
+}
+
+/// Like [`try_init`], but writing happens off the calling thread: each call
+/// only formats the line and hands it to a bounded queue
+/// of size `queue_size`, which a dedicated background thread drains to
+/// stdout/stderr. `overflow` decides what happens when that queue is full.
+/// [`log::logger().flush()`] blocks until the queue has been drained.
+pub fn try_init_nonblocking(
+    finn_app: &'static str,
+    env: FiaasEnv,
+    level: Level,
+    queue_size: usize,
+    overflow: OverflowPolicy,
+) -> Result<(), log::SetLoggerError> {
+    let (sender, receiver) = mpsc::sync_channel(queue_size);
+    spawn_writer_thread(receiver);
+    let output = Output::NonBlocking(NonBlockingWriter {
+        sender,
+        overflow,
+        dropped: Arc::new(AtomicU64::new(0)),
+    });
+    try_init_inner(finn_app, env, FilterSpec::from_level(level), None, output)
+}
+
+/// The number of log lines dropped so far because the non-blocking writer's
+/// queue was full and [`OverflowPolicy::Drop`] was in effect. Always `0`
+/// outside of [`try_init_nonblocking`] with that policy. Panics if no logger
+/// has been installed via `try_init*`.
+pub fn dropped_log_count() -> u64 {
+    match &installed_logger().read().unwrap().output {
+        Output::NonBlocking(writer) => writer.dropped.load(Ordering::Relaxed),
+        Output::Stdio | Output::File { .. } => 0,
+    }
+}
+
+fn try_init_with_filter(
+    finn_app: &'static str,
+    env: FiaasEnv,
+    filter: FilterSpec,
+) -> Result<(), log::SetLoggerError> {
+    try_init_inner(finn_app, env, filter, None, Output::Stdio)
+}
+
+fn try_init_inner(
+    finn_app: &'static str,
+    env: FiaasEnv,
+    filter: FilterSpec,
+    formatter: Option<Box<Formatter>>,
+    output: Output,
+) -> Result<(), log::SetLoggerError> {
+    let max_level = filter.max_level();
+    let logger = Arc::new(RwLock::new(FiaasLogger {
         finn_app,
         env,
-        level,
+        filter,
+        formatter,
+        output,
+    }));
+    let r = log::set_boxed_logger(Box::new(FiaasLoggerShim {
+        inner: Arc::clone(&logger),
     }));
     if r.is_ok() {
-        log::set_max_level(level.to_level_filter());
+        // Only the first successful `try_init*` call gets here, so `LOGGER`
+        // always holds the config backing the logger `log` actually uses.
+        let _ = LOGGER.set(logger);
+        log::set_max_level(max_level);
     }
     r
 }
 
-pub fn init(finn_app: &'static str, env: FiaasEnv, level: Level) {
-    try_init(finn_app, env, level).unwrap();
+/// Swaps the env and level of an already-installed logger in place, without
+/// reinstalling it. Panics if no logger has been installed via `try_init*`.
+pub fn reconfigure(env: FiaasEnv, level: Level) {
+    reconfigure_with_filter(env, FilterSpec::from_level(level));
+}
+
+/// Swaps the filter of an already-installed logger in place, parsing `spec`
+/// the same way [`init_with_filter`] does. Panics if no logger has been
+/// installed via `try_init*`.
+pub fn set_filter(spec: &str) {
+    let logger = installed_logger();
+    let filter = FilterSpec::parse(spec);
+    log::set_max_level(filter.max_level());
+    logger.write().unwrap().filter = filter;
+}
+
+fn reconfigure_with_filter(env: FiaasEnv, filter: FilterSpec) {
+    let logger = installed_logger();
+    log::set_max_level(filter.max_level());
+    let mut logger = logger.write().unwrap();
+    logger.env = env;
+    logger.filter = filter;
+}
+
+fn installed_logger() -> &'static Arc<RwLock<FiaasLogger>> {
+    LOGGER
+        .get()
+        .expect("no logger installed; call try_init, init or init_env first")
 }
 
 pub fn init_env(finn_app: &'static str) {
-    let level = match std::env::var("RUST_LOG")
-        .expect("RUST_LOG must be set")
-        .as_ref()
-    {
-        "error" => Level::Error,
-        "warn" => Level::Warn,
-        "info" => Level::Info,
-        "debug" => Level::Debug,
-        "trace" => Level::Trace,
-        _ => panic!("RUST_LOG must be one of error, warn, info, debug and trace"),
-    };
+    let spec = std::env::var("RUST_LOG").expect("RUST_LOG must be set");
 
     let env = match std::env::var("FIAAS_ENVIRONMENT")
         .expect("FIAAS_ENVIRONMENT must be set")
@@ -115,7 +575,7 @@ pub fn init_env(finn_app: &'static str) {
         _ => panic!("FIAAS_ENVIRONMENT must be one of local, dev and prod"),
     };
 
-    init(finn_app, env, level);
+    init_with_filter(finn_app, env, &spec).unwrap();
 }
 
 #[cfg(test)]
@@ -127,6 +587,63 @@ mod tests {
         std::env::set_var("FIAAS_ENVIRONMENT", "local");
         std::env::set_var("RUST_LOG", "warn");
         init_env("test");
+
+        // `log` only ever lets one logger be installed per process, so this
+        // is also the only place we can exercise reconfiguring it in place.
+        reconfigure(FiaasEnv::Dev, Level::Debug);
+        set_filter("info,my_app=trace");
+        let max_level = installed_logger().read().unwrap().filter.max_level();
+        assert_eq!(LevelFilter::Trace, max_level);
+    }
+
+    #[test]
+    fn filter_spec_falls_back_to_default_level() {
+        let filter = FilterSpec::parse("info,hyper=warn,my_app::db=trace");
+        assert_eq!(LevelFilter::Info, filter.level_for("my_app"));
+    }
+
+    #[test]
+    fn filter_spec_matches_longest_prefix() {
+        let filter = FilterSpec::parse("info,my_app=warn,my_app::db=trace");
+        assert_eq!(LevelFilter::Trace, filter.level_for("my_app::db::pool"));
+        assert_eq!(LevelFilter::Warn, filter.level_for("my_app::http"));
+    }
+
+    #[test]
+    fn filter_spec_max_level_is_loosest_of_all_directives() {
+        let filter = FilterSpec::parse("warn,my_app::db=trace");
+        assert_eq!(LevelFilter::Trace, filter.max_level());
+    }
+
+    #[test]
+    fn log_format_fiaas_merges_key_values() {
+        let timestamp = humantime::format_rfc3339_millis(SystemTime::now());
+        let key_values = [("user_id", 42i64)];
+        let record = Record::builder()
+            .args(format_args!("checkout done"))
+            .level(Level::Info)
+            .target("test")
+            .key_values(&key_values)
+            .build();
+        let produced_log = format_log_fiaas(&timestamp, &record, "test");
+        let produced: serde_json::Value = serde_json::from_str(&produced_log).unwrap();
+        assert_eq!(42, produced["user_id"]);
+    }
+
+    #[test]
+    fn log_format_fiaas_namespaces_colliding_key_values() {
+        let timestamp = humantime::format_rfc3339_millis(SystemTime::now());
+        let key_values = [("message", "not the real message")];
+        let record = Record::builder()
+            .args(format_args!("Error!"))
+            .level(Level::Error)
+            .target("test")
+            .key_values(&key_values)
+            .build();
+        let produced_log = format_log_fiaas(&timestamp, &record, "test");
+        let produced: serde_json::Value = serde_json::from_str(&produced_log).unwrap();
+        assert_eq!("Error!", produced["message"]);
+        assert_eq!("not the real message", produced["field_message"]);
     }
 
     #[test]
@@ -166,4 +683,70 @@ mod tests {
         );
         assert_eq!(sample_log, produced_log);
     }
+
+    #[test]
+    fn custom_formatter_renders_the_line() {
+        let timestamp = humantime::format_rfc3339_millis(SystemTime::now());
+        let record = Record::builder()
+            .args(format_args!("hi"))
+            .level(Level::Info)
+            .target("test")
+            .build();
+        let formatter: Box<Formatter> = Box::new(
+            |sink: &mut dyn Write, record: &Record, _timestamp: &Rfc3339Timestamp| {
+                writeln!(sink, "custom: {}", record.args())
+            },
+        );
+
+        let mut buf = Vec::new();
+        formatter(&mut buf, &record, &timestamp).unwrap();
+        assert_eq!("custom: hi\n", String::from_utf8(buf).unwrap());
+    }
+
+    #[test]
+    fn file_output_writes_and_flushes_formatted_lines() {
+        let path = std::env::temp_dir().join(format!(
+            "fiaas-logger-test-{:?}.log",
+            thread::current().id()
+        ));
+        let file = IfExists::Truncate.open(&path).unwrap();
+        let logger = FiaasLogger {
+            finn_app: "test",
+            env: FiaasEnv::Local,
+            filter: FilterSpec::from_level(Level::Info),
+            formatter: None,
+            output: Output::File {
+                writer: Mutex::new(BufWriter::new(file)),
+            },
+        };
+
+        let record = Record::builder()
+            .args(format_args!("hello"))
+            .level(Level::Info)
+            .target("test")
+            .build();
+        logger.log(&record);
+        logger.flush();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(contents.contains("hello"));
+    }
+
+    #[test]
+    fn non_blocking_writer_drops_and_counts_on_overflow() {
+        let (sender, _receiver) = mpsc::sync_channel(1);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer = NonBlockingWriter {
+            sender,
+            overflow: OverflowPolicy::Drop,
+            dropped: Arc::clone(&dropped),
+        };
+
+        writer.enqueue("first".to_string(), false).unwrap();
+        writer.enqueue("second".to_string(), false).unwrap();
+        writer.enqueue("third".to_string(), false).unwrap();
+
+        assert_eq!(2, dropped.load(Ordering::Relaxed));
+    }
 }